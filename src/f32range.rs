@@ -24,6 +24,17 @@ impl F32Range {
         self.high = self.high.max(v);
     }
 
+    /// Merges two ranges into the smallest range covering both.
+    ///
+    /// `empty()` is the identity element, which makes this suitable as the
+    /// combining operation of a parallel reduction.
+    pub fn merge(self, other: F32Range) -> Self {
+        Self {
+            low: self.low.min(other.low),
+            high: self.high.max(other.high),
+        }
+    }
+
     pub fn t(self, v: f32) -> f32 {
         (v - self.low) / (self.high - self.low)
     }
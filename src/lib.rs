@@ -1,4 +1,7 @@
 mod f32range;
+pub mod palette;
+
+use rayon::prelude::*;
 
 use f32range::F32Range;
 
@@ -58,6 +61,10 @@ impl System {
         self.height
     }
 
+    pub fn kernel(&self) -> &[f32; 9] {
+        &self.kernel
+    }
+
     /// Warning: be sure to call `update_metadata` after a call to `set` as it's not done
     /// automatically.
     pub fn set(&mut self, (x, y): (usize, usize), (a, b): Cell) {
@@ -92,45 +99,89 @@ impl System {
     /// Evolves the current state of the system
     ///
     /// It also updated the metadata because it's quite cheap to do here.
-    #[allow(clippy::many_single_char_names)]
     pub fn evolve(&mut self, dt: f32) {
         let (da, db) = self.diffusion_rates;
         let f = self.feed_rate;
         let k = self.kill_rate;
 
-        self.b_range = F32Range::empty();
-
-        for (i, nc) in self.world_buffer.iter_mut().enumerate() {
-            let (x, y) = (i % self.width, i / self.width);
-
-            let lx = if x == 0 { self.width - 1 } else { x - 1 };
-            let rx = (x + 1) % self.width;
+        let (width, height) = (self.width, self.height);
+        let world = &self.world;
+        let kernel = &self.kernel;
+
+        let step = |i: usize, nc: &mut Cell| {
+            *nc = Self::step_cell(world, kernel, width, height, da, db, f, k, dt, i);
+            let mut r = F32Range::empty();
+            r.expand(nc.1);
+            r
+        };
+
+        // small grids don't amortize the thread pool overhead, so evolve them
+        // on the current thread.
+        self.b_range = if width * height >= PAR_THRESHOLD {
+            self.world_buffer
+                .par_iter_mut()
+                .enumerate()
+                .map(|(i, nc)| step(i, nc))
+                .reduce(F32Range::empty, F32Range::merge)
+        } else {
+            self.world_buffer
+                .iter_mut()
+                .enumerate()
+                .map(|(i, nc)| step(i, nc))
+                .fold(F32Range::empty(), F32Range::merge)
+        };
 
-            let ty = if y == 0 { self.height - 1 } else { y - 1 };
-            let by = (y + 1) % self.height;
+        std::mem::swap(&mut self.world, &mut self.world_buffer);
+    }
 
-            #[rustfmt::skip]
-            let neighbors = [
-                (lx, ty), (x, ty), (rx, ty),
-                (lx,  y), (x,  y), (rx,  y),
-                (lx, by), (x, by), (rx, by),
-            ];
-
-            let mut neighbors_a = 0.0;
-            let mut neighbors_b = 0.0;
-            for ((xx, yy), k) in neighbors.iter().zip(&self.kernel) {
-                let (a, b) = self.world[yy * self.width + xx];
-                neighbors_a += k * a;
-                neighbors_b += k * b;
-            }
-
-            let (a, b) = self.world[i];
-            nc.0 = a + dt * (da * neighbors_a - a * b.powi(2) + f * (1.0 - a));
-            nc.1 = b + dt * (db * neighbors_b + a * b.powi(2) - (k + f) * b);
-
-            self.b_range.expand(nc.1);
+    /// Computes the next value of the cell at linear index `i` from the
+    /// read-only `world`, leaving it free of any shared mutable state so it can
+    /// run in parallel.
+    #[allow(clippy::many_single_char_names)]
+    #[allow(clippy::too_many_arguments)]
+    fn step_cell(
+        world: &[Cell],
+        kernel: &[f32; 9],
+        width: usize,
+        height: usize,
+        da: f32,
+        db: f32,
+        f: f32,
+        k: f32,
+        dt: f32,
+        i: usize,
+    ) -> Cell {
+        let (x, y) = (i % width, i / width);
+
+        let lx = if x == 0 { width - 1 } else { x - 1 };
+        let rx = (x + 1) % width;
+
+        let ty = if y == 0 { height - 1 } else { y - 1 };
+        let by = (y + 1) % height;
+
+        #[rustfmt::skip]
+        let neighbors = [
+            (lx, ty), (x, ty), (rx, ty),
+            (lx,  y), (x,  y), (rx,  y),
+            (lx, by), (x, by), (rx, by),
+        ];
+
+        let mut neighbors_a = 0.0;
+        let mut neighbors_b = 0.0;
+        for ((xx, yy), k) in neighbors.iter().zip(kernel) {
+            let (a, b) = world[yy * width + xx];
+            neighbors_a += k * a;
+            neighbors_b += k * b;
         }
 
-        std::mem::swap(&mut self.world, &mut self.world_buffer);
+        let (a, b) = world[i];
+        (
+            a + dt * (da * neighbors_a - a * b.powi(2) + f * (1.0 - a)),
+            b + dt * (db * neighbors_b + a * b.powi(2) - (k + f) * b),
+        )
     }
 }
+
+/// Grids with at least this many cells are evolved in parallel; smaller ones
+/// stay single threaded to avoid paying the thread-pool overhead.
+const PAR_THRESHOLD: usize = 1 << 14;
@@ -2,14 +2,21 @@ use std::convert::TryFrom;
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::{Child, ChildStdin, Command, Stdio};
 use std::time::Instant;
 
 use rand::prelude::*;
 use structopt::StructOpt;
 
+use rd::palette::Palette;
 use rd::System;
 
+mod meta;
+mod preview;
+
+use meta::RenderParams;
+use preview::{Preview, PreviewTarget};
+
 /// Program to generate images and even videos showing
 /// what Reaction Diffusion is all about.
 #[derive(Debug, StructOpt)]
@@ -46,10 +53,38 @@ struct Opts {
     #[structopt(long)]
     without_video: bool,
 
+    /// Save every sampled frame as a PNG in `img_dir` and assemble the video
+    /// from them instead of streaming raw frames straight into ffmpeg.
+    ///
+    /// Slower and disk heavy, kept around as a fallback for environments where
+    /// piping into ffmpeg is not an option.
+    #[structopt(long)]
+    png_frames: bool,
+
     /// Where to store the temporary frames used to create the video.
+    ///
+    /// Only used together with `--png-frames`.
     #[structopt(long, parse(from_os_str), default_value = "img")]
     img_dir: PathBuf,
 
+    /// Watch the simulation evolve live in the terminal using a graphics
+    /// protocol, throttled by `speed`.
+    #[structopt(long)]
+    preview: bool,
+
+    /// Which terminal graphics protocol to use for `--preview`.
+    #[structopt(long, default_value = "auto", possible_values = &["auto", "kitty", "sixel"])]
+    preview_target: PreviewTarget,
+
+    /// Color palette used to map the simulation onto colors.
+    #[structopt(long, default_value = "grayscale", possible_values = &["grayscale", "gray", "inferno", "heat"])]
+    palette: String,
+
+    /// Custom palette as `stop:rrggbb` control points, comma separated, e.g.
+    /// `0:000000,0.5:ff0000,1:ffffff`. Overrides `--palette` when given.
+    #[structopt(long)]
+    palette_stops: Option<String>,
+
     /// The seed to use to start the generation.
     #[structopt(subcommand)]
     seed: Option<Seed>,
@@ -70,19 +105,43 @@ enum Seed {
         #[structopt(parse(from_os_str))]
         input: PathBuf,
     },
+
+    /// Redo the simulation stored in the metadata of a previously rendered
+    /// PNG, reusing its parameters.
+    Redo {
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+    },
 }
 
 struct Renderer {
     with_video: bool,
+    png_frames: bool,
     speed: usize,
     img_dir: PathBuf,
-    tmp_img: image::GrayImage,
+    palette: Palette,
+    params: RenderParams,
+    tmp_img: image::RgbImage,
+
+    // ffmpeg process raw frames are streamed into, `None` when frames are
+    // written out as PNGs instead.
+    ffmpeg: Option<Child>,
+    ffmpeg_stdin: Option<ChildStdin>,
 }
 
 fn main() {
     let opts = Opts::from_args();
 
-    setup_img_dir(&opts);
+    // a `redo` reconstructs the parameters straight out of the picture's
+    // metadata and re-runs with them.
+    let opts = match &opts.seed {
+        Some(Seed::Redo { input }) => RenderParams::read_png(input).into_opts(),
+        _ => opts,
+    };
+
+    if opts.png_frames {
+        setup_img_dir(&opts);
+    }
 
     let start_ts = Instant::now();
 
@@ -91,16 +150,38 @@ fn main() {
     let stdout = std::io::stdout();
     let mut stdout = stdout.lock();
 
-    let mut renderer = Renderer::new(&opts);
+    let palette = build_palette(&opts);
+    let params = RenderParams::from_run(&opts, &system);
+
+    let mut renderer = Renderer::new(&opts, palette.clone(), params);
     renderer.start(&system);
 
+    let mut preview = if opts.preview {
+        Some(Preview::new(
+            opts.preview_target,
+            opts.width.into(),
+            opts.height.into(),
+            palette,
+        ))
+    } else {
+        None
+    };
+
     for i in 1..=opts.iterations {
-        write!(stdout, "\riteration: {}", i).unwrap();
-        stdout.flush().unwrap();
+        if preview.is_none() {
+            write!(stdout, "\riteration: {}", i).unwrap();
+            stdout.flush().unwrap();
+        }
 
         system.evolve(1.0);
 
         renderer.snapshot(&system, i);
+
+        if let Some(preview) = preview.as_mut() {
+            if i % opts.speed == 0 {
+                preview.show(&system);
+            }
+        }
     }
 
     let elapsed = start_ts.elapsed();
@@ -120,6 +201,13 @@ generation took {} min {} secs
     .unwrap();
 }
 
+fn build_palette(opts: &Opts) -> Palette {
+    match &opts.palette_stops {
+        Some(spec) => Palette::parse(spec).unwrap(),
+        None => Palette::from_name(&opts.palette).unwrap(),
+    }
+}
+
 fn create_system(opts: &Opts) -> System {
     let mut system = System::new(opts.width.into(), opts.height.into());
     system.feed_rate = opts.feed_rate;
@@ -129,7 +217,7 @@ fn create_system(opts: &Opts) -> System {
     let height = system.height();
 
     match &opts.seed {
-        None | Some(Seed::Rect) => {
+        None | Some(Seed::Rect) | Some(Seed::Redo { .. }) => {
             let l = width.min(height) / 4;
             let ty = height / 2 - l / 2;
             let sx = width / 2 - l / 2;
@@ -194,49 +282,120 @@ fn setup_img_dir(opts: &Opts) {
 }
 
 impl Renderer {
-    fn new(opts: &Opts) -> Self {
+    fn new(opts: &Opts, palette: Palette, params: RenderParams) -> Self {
         let with_video = !opts.without_video && Self::can_build_video();
 
         Self {
             img_dir: opts.img_dir.clone(),
             with_video,
+            png_frames: opts.png_frames,
             speed: opts.speed,
+            palette,
+            params,
+
+            tmp_img: image::RgbImage::new(opts.width.into(), opts.height.into()),
 
-            tmp_img: image::GrayImage::new(opts.width.into(), opts.height.into()),
+            ffmpeg: None,
+            ffmpeg_stdin: None,
         }
     }
 
     fn start(&mut self, system: &System) {
-        if self.with_video {
+        if !self.with_video {
+            return;
+        }
+
+        if self.png_frames {
             let path = self.img_dir.join("rd-0.png");
             self.render_frame(system, &path);
+        } else {
+            self.spawn_ffmpeg();
+            self.stream_frame(system);
         }
     }
 
     fn snapshot(&mut self, system: &System, gen: usize) {
         assert!(gen > 0);
 
-        if self.with_video && gen % self.speed == 0 {
+        if !self.with_video || gen % self.speed != 0 {
+            return;
+        }
+
+        if self.png_frames {
             let path = self.img_dir.join(&format!("rd-{}.png", gen / self.speed));
             self.render_frame(&system, &path);
+        } else {
+            self.stream_frame(system);
         }
     }
 
     fn end(&mut self, system: &System) {
         self.render_frame(&system, Path::new("rd.png"));
 
-        if self.with_video {
+        if !self.with_video {
+            return;
+        }
+
+        if self.png_frames {
             self.build_video();
+        } else {
+            // closing stdin tells ffmpeg it reached the end of the stream.
+            self.ffmpeg_stdin.take();
+            if let Some(mut ffmpeg) = self.ffmpeg.take() {
+                ffmpeg.wait().unwrap();
+            }
         }
     }
 
-    fn render_frame(&mut self, system: &System, path: &Path) {
+    /// Fills `tmp_img` with the current RGB representation of the system,
+    /// mapping each cell through the configured palette.
+    fn fill_frame(&mut self, system: &System) {
+        let range = system.b_range();
         for ((_, c), pix) in system.cells().zip(self.tmp_img.pixels_mut()) {
-            let g = system.b_range().t(c.1) * 255.0;
-            *pix = image::Luma([g as u8]);
+            *pix = image::Rgb(self.palette.sample(range.t(c.1)));
         }
+    }
+
+    fn render_frame(&mut self, system: &System, path: &Path) {
+        self.fill_frame(system);
+        self.params.write_png(path, &self.tmp_img);
+    }
+
+    fn spawn_ffmpeg(&mut self) {
+        let (width, height) = (self.tmp_img.width(), self.tmp_img.height());
+
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args(&[
+                "-f",
+                "rawvideo",
+                "-pix_fmt",
+                "rgb24",
+                "-s",
+                &format!("{}x{}", width, height),
+                "-framerate",
+                "60",
+                "-i",
+                "-",
+                "-pix_fmt",
+                "yuv420p",
+                "-y",
+                "rd.mp4",
+            ])
+            .stdin(Stdio::piped())
+            .spawn()
+            .unwrap();
 
-        self.tmp_img.save(path).unwrap();
+        self.ffmpeg_stdin = ffmpeg.stdin.take();
+        self.ffmpeg = Some(ffmpeg);
+    }
+
+    /// Writes the current frame as raw RGB bytes straight into ffmpeg.
+    fn stream_frame(&mut self, system: &System) {
+        self.fill_frame(system);
+
+        if let Some(stdin) = self.ffmpeg_stdin.as_mut() {
+            stdin.write_all(&self.tmp_img).unwrap();
+        }
     }
 
     fn can_build_video() -> bool {
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use rd::System;
+
+use crate::preview::PreviewTarget;
+use crate::{Opts, Seed};
+
+/// The parameters that fully describe a run, embedded into every PNG as `tEXt`
+/// chunks so a produced image carries everything needed to regenerate it.
+#[derive(Debug, Clone)]
+pub struct RenderParams {
+    feed_rate: f32,
+    kill_rate: f32,
+    diffusion_rates: (f32, f32),
+    width: u16,
+    height: u16,
+    iterations: usize,
+    seed: String,
+    kernel: [f32; 9],
+    palette: String,
+    palette_stops: Option<String>,
+}
+
+impl RenderParams {
+    pub fn from_run(opts: &Opts, system: &System) -> Self {
+        Self {
+            feed_rate: opts.feed_rate,
+            kill_rate: opts.kill_rate,
+            diffusion_rates: system.diffusion_rates,
+            width: opts.width,
+            height: opts.height,
+            iterations: opts.iterations,
+            seed: seed_to_string(&opts.seed),
+            kernel: *system.kernel(),
+            palette: opts.palette.clone(),
+            palette_stops: opts.palette_stops.clone(),
+        }
+    }
+
+    /// Saves `img` as a PNG with the parameters attached as text chunks.
+    pub fn write_png(&self, path: &Path, img: &image::RgbImage) {
+        let file = File::create(path).unwrap();
+
+        let mut encoder = png::Encoder::new(BufWriter::new(file), img.width(), img.height());
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        for (keyword, text) in self.text_chunks() {
+            encoder.add_text_chunk(keyword.to_string(), text).unwrap();
+        }
+
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(img).unwrap();
+    }
+
+    /// Reads the parameters back from the text chunks of an existing PNG.
+    pub fn read_png(path: &Path) -> Self {
+        let decoder = png::Decoder::new(File::open(path).unwrap());
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+
+        let mut chunks = HashMap::new();
+        for chunk in &info.uncompressed_latin1_text {
+            chunks.insert(chunk.keyword.clone(), chunk.text.clone());
+        }
+        for chunk in &info.utf8_text {
+            if let Ok(text) = chunk.get_text() {
+                chunks.insert(chunk.keyword.clone(), text);
+            }
+        }
+
+        Self::from_chunks(&chunks)
+    }
+
+    /// Rebuilds the `Opts` that would reproduce this run.
+    pub fn into_opts(self) -> Opts {
+        Opts {
+            width: self.width,
+            height: self.height,
+            iterations: self.iterations,
+            feed_rate: self.feed_rate,
+            kill_rate: self.kill_rate,
+            speed: 1,
+            without_video: false,
+            png_frames: false,
+            img_dir: PathBuf::from("img"),
+            preview: false,
+            preview_target: PreviewTarget::Auto,
+            palette: self.palette,
+            palette_stops: self.palette_stops,
+            seed: seed_from_string(&self.seed),
+        }
+    }
+
+    fn text_chunks(&self) -> Vec<(&'static str, String)> {
+        let kernel = self
+            .kernel
+            .iter()
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut chunks = vec![
+            ("feed_rate", self.feed_rate.to_string()),
+            ("kill_rate", self.kill_rate.to_string()),
+            (
+                "diffusion_rates",
+                format!("{} {}", self.diffusion_rates.0, self.diffusion_rates.1),
+            ),
+            ("width", self.width.to_string()),
+            ("height", self.height.to_string()),
+            ("iterations", self.iterations.to_string()),
+            ("seed", self.seed.clone()),
+            ("kernel", kernel),
+            ("palette", self.palette.clone()),
+        ];
+        if let Some(stops) = &self.palette_stops {
+            chunks.push(("palette_stops", stops.clone()));
+        }
+        chunks
+    }
+
+    fn from_chunks(chunks: &HashMap<String, String>) -> Self {
+        let get = |k: &str| chunks.get(k).map(String::as_str);
+
+        let diffusion_rates = get("diffusion_rates")
+            .and_then(|s| {
+                let mut it = s.split_whitespace();
+                Some((it.next()?.parse().ok()?, it.next()?.parse().ok()?))
+            })
+            .unwrap_or((1.0, 0.5));
+
+        let mut kernel = [0.0; 9];
+        if let Some(s) = get("kernel") {
+            for (slot, v) in kernel.iter_mut().zip(s.split_whitespace()) {
+                *slot = v.parse().unwrap_or(0.0);
+            }
+        }
+
+        Self {
+            feed_rate: get("feed_rate").and_then(|s| s.parse().ok()).unwrap_or(0.055),
+            kill_rate: get("kill_rate").and_then(|s| s.parse().ok()).unwrap_or(0.062),
+            diffusion_rates,
+            width: get("width").and_then(|s| s.parse().ok()).unwrap_or(512),
+            height: get("height").and_then(|s| s.parse().ok()).unwrap_or(512),
+            iterations: get("iterations").and_then(|s| s.parse().ok()).unwrap_or(300),
+            seed: get("seed").unwrap_or("rect").to_string(),
+            kernel,
+            palette: get("palette").unwrap_or("grayscale").to_string(),
+            palette_stops: get("palette_stops").map(str::to_string),
+        }
+    }
+}
+
+fn seed_to_string(seed: &Option<Seed>) -> String {
+    match seed {
+        None | Some(Seed::Rect) | Some(Seed::Redo { .. }) => "rect".to_string(),
+        Some(Seed::Random) => "random".to_string(),
+        Some(Seed::Image { input }) => format!("image:{}", input.display()),
+    }
+}
+
+fn seed_from_string(seed: &str) -> Option<Seed> {
+    if seed == "random" {
+        Some(Seed::Random)
+    } else if let Some(path) = seed.strip_prefix("image:") {
+        Some(Seed::Image {
+            input: PathBuf::from(path),
+        })
+    } else {
+        Some(Seed::Rect)
+    }
+}
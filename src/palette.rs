@@ -0,0 +1,118 @@
+/// A color gradient mapping a normalized `t` in `[0, 1]` onto an RGB color.
+///
+/// The gradient is described as an ordered list of `(stop, [r, g, b])` control
+/// points; colors in between are linearly interpolated and values outside the
+/// first and last stop are clamped to them.
+#[derive(Debug, Clone)]
+pub struct Palette {
+    stops: Vec<(f32, [u8; 3])>,
+}
+
+impl Palette {
+    /// Builds a palette from its control points, sorting them by stop.
+    ///
+    /// Panics if no control point is given.
+    pub fn new(mut stops: Vec<(f32, [u8; 3])>) -> Self {
+        assert!(!stops.is_empty(), "a palette needs at least one stop");
+        stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        Self { stops }
+    }
+
+    /// Maps a normalized value onto its color.
+    pub fn sample(&self, t: f32) -> [u8; 3] {
+        // a degenerate `b_range` yields a non finite `t`, just pin it to the
+        // bottom of the gradient in that case.
+        let t = if t.is_finite() { t.clamp(0.0, 1.0) } else { 0.0 };
+
+        if t <= self.stops[0].0 {
+            return self.stops[0].1;
+        }
+
+        for w in self.stops.windows(2) {
+            let (lo, lc) = w[0];
+            let (hi, hc) = w[1];
+            if t <= hi {
+                let f = if hi > lo { (t - lo) / (hi - lo) } else { 0.0 };
+                return [
+                    lerp(lc[0], hc[0], f),
+                    lerp(lc[1], hc[1], f),
+                    lerp(lc[2], hc[2], f),
+                ];
+            }
+        }
+
+        self.stops[self.stops.len() - 1].1
+    }
+
+    /// Classic black to white grayscale ramp.
+    pub fn grayscale() -> Self {
+        Self::new(vec![(0.0, [0, 0, 0]), (1.0, [255, 255, 255])])
+    }
+
+    /// Perceptual dark-to-bright ramp in the spirit of matplotlib's inferno.
+    pub fn inferno() -> Self {
+        Self::new(vec![
+            (0.0, [0, 0, 4]),
+            (0.25, [87, 16, 110]),
+            (0.5, [188, 55, 84]),
+            (0.75, [249, 142, 9]),
+            (1.0, [252, 255, 164]),
+        ])
+    }
+
+    /// Two-color heat ramp going from deep blue through red to white.
+    pub fn heat() -> Self {
+        Self::new(vec![
+            (0.0, [0, 0, 32]),
+            (0.5, [200, 30, 30]),
+            (1.0, [255, 255, 255]),
+        ])
+    }
+
+    /// Looks up one of the bundled presets by name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "grayscale" | "gray" => Some(Self::grayscale()),
+            "inferno" => Some(Self::inferno()),
+            "heat" => Some(Self::heat()),
+            _ => None,
+        }
+    }
+
+    /// Parses a custom palette from a `stop:rrggbb` comma separated spec, e.g.
+    /// `0:000000,0.5:ff0000,1:ffffff`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut stops = Vec::new();
+        for part in spec.split(',') {
+            let (stop, color) = part
+                .split_once(':')
+                .ok_or_else(|| format!("missing `:` in palette stop: {}", part))?;
+
+            let stop = stop
+                .trim()
+                .parse::<f32>()
+                .map_err(|_| format!("invalid palette stop: {}", stop))?;
+
+            let color = color.trim().trim_start_matches('#');
+            if color.len() != 6 {
+                return Err(format!("expected a rrggbb color, got: {}", color));
+            }
+            let channel = |i: usize| {
+                u8::from_str_radix(&color[i..i + 2], 16)
+                    .map_err(|_| format!("invalid color component in: {}", color))
+            };
+
+            stops.push((stop, [channel(0)?, channel(2)?, channel(4)?]));
+        }
+
+        if stops.is_empty() {
+            return Err("empty palette".to_string());
+        }
+
+        Ok(Self::new(stops))
+    }
+}
+
+fn lerp(a: u8, b: u8, t: f32) -> u8 {
+    (f32::from(a) + (f32::from(b) - f32::from(a)) * t).round() as u8
+}
@@ -0,0 +1,214 @@
+use std::env;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use rd::palette::Palette;
+use rd::System;
+
+/// Terminal graphics protocol used to draw the live preview.
+#[derive(Debug, Clone, Copy)]
+pub enum PreviewTarget {
+    /// Detect the best protocol from the environment, preferring kitty and
+    /// falling back to sixel.
+    Auto,
+    Kitty,
+    Sixel,
+}
+
+impl FromStr for PreviewTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(PreviewTarget::Auto),
+            "kitty" => Ok(PreviewTarget::Kitty),
+            "sixel" => Ok(PreviewTarget::Sixel),
+            _ => Err(format!("unknown preview target: {}", s)),
+        }
+    }
+}
+
+impl PreviewTarget {
+    /// Resolves `Auto` to a concrete protocol by sniffing the environment.
+    fn resolve(self) -> PreviewTarget {
+        match self {
+            PreviewTarget::Auto => {
+                let kitty = env::var_os("KITTY_WINDOW_ID").is_some()
+                    || env::var("TERM").map_or(false, |t| t.contains("kitty"));
+
+                if kitty {
+                    PreviewTarget::Kitty
+                } else {
+                    PreviewTarget::Sixel
+                }
+            }
+            t => t,
+        }
+    }
+}
+
+/// Draws the system in place on the terminal after each sampled generation.
+pub struct Preview {
+    target: PreviewTarget,
+    width: usize,
+    height: usize,
+    palette: Palette,
+
+    // RGB buffer, `width * height * 3` bytes, reused across frames.
+    buf: Vec<u8>,
+}
+
+impl Preview {
+    pub fn new(target: PreviewTarget, width: usize, height: usize, palette: Palette) -> Self {
+        Self {
+            target: target.resolve(),
+            width,
+            height,
+            palette,
+            buf: vec![0; width * height * 3],
+        }
+    }
+
+    /// Encodes the current state of the system and emits it in place.
+    pub fn show(&mut self, system: &System) {
+        self.fill(system);
+
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+
+        // reposition the cursor to the top-left so every frame overdraws the
+        // previous one.
+        out.write_all(b"\x1b[H").unwrap();
+
+        match self.target {
+            PreviewTarget::Kitty => self.emit_kitty(&mut out),
+            // `Auto` is resolved away in `new`, so treat anything else as sixel.
+            _ => self.emit_sixel(&mut out),
+        }
+
+        out.flush().unwrap();
+    }
+
+    fn fill(&mut self, system: &System) {
+        let range = system.b_range();
+        for ((_, c), px) in system.cells().zip(self.buf.chunks_mut(3)) {
+            px.copy_from_slice(&self.palette.sample(range.t(c.1)));
+        }
+    }
+
+    fn emit_kitty(&self, out: &mut impl Write) {
+        let encoded = base64(&self.buf);
+        let bytes = encoded.as_bytes();
+
+        let mut i = 0;
+        let mut first = true;
+        while i < bytes.len() {
+            let end = (i + 4096).min(bytes.len());
+            let more = if end == bytes.len() { 0 } else { 1 };
+
+            if first {
+                // the control keys (image format, size and the transmit-and-
+                // display action) only belong on the first chunk; continuation
+                // chunks carry just the `m` flag.
+                write!(
+                    out,
+                    "\x1b_Ga=T,f=24,s={},v={},m={};",
+                    self.width, self.height, more
+                )
+                .unwrap();
+            } else {
+                write!(out, "\x1b_Gm={};", more).unwrap();
+            }
+            out.write_all(&bytes[i..end]).unwrap();
+            out.write_all(b"\x1b\\").unwrap();
+
+            first = false;
+            i = end;
+        }
+    }
+
+    /// Emits the frame as sixels.
+    ///
+    /// Sixel is a paletted format and this fallback only renders shades of
+    /// gray, so colored palettes are collapsed to their luminance here.
+    fn emit_sixel(&self, out: &mut impl Write) {
+        // number of gray levels the image is quantized down to.
+        const LEVELS: usize = 16;
+
+        let mut s = String::from("\x1bPq");
+        for n in 0..LEVELS {
+            let v = n * 100 / (LEVELS - 1);
+            s.push_str(&format!("#{};2;{};{};{}", n, v, v, v));
+        }
+
+        let (w, h) = (self.width, self.height);
+        let mut y = 0;
+        while y < h {
+            // every band packs six vertical pixels into a single sixel byte,
+            // one pass per color.
+            for n in 0..LEVELS {
+                s.push_str(&format!("#{}", n));
+                for x in 0..w {
+                    let mut bits = 0u8;
+                    for row in 0..6 {
+                        let yy = y + row;
+                        if yy >= h {
+                            continue;
+                        }
+
+                        let px = (yy * w + x) * 3;
+                        let g = luminance(self.buf[px], self.buf[px + 1], self.buf[px + 2]);
+                        let level = (g as usize * (LEVELS - 1) + 127) / 255;
+                        if level == n {
+                            bits |= 1 << row;
+                        }
+                    }
+                    s.push((63 + bits) as char);
+                }
+                // overlay the next color on top of the same band.
+                s.push('$');
+            }
+            s.push('-');
+            y += 6;
+        }
+        s.push_str("\x1b\\");
+
+        out.write_all(s.as_bytes()).unwrap();
+    }
+}
+
+/// Rec. 601 luma of an RGB triple, used to collapse colored palettes onto the
+/// grayscale sixel fallback.
+fn luminance(r: u8, g: u8, b: u8) -> u8 {
+    (0.299 * f32::from(r) + 0.587 * f32::from(g) + 0.114 * f32::from(b)) as u8
+}
+
+/// Minimal standard base64 encoder, enough to wrap the kitty payload.
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+
+        out.push(TABLE[(n >> 18 & 63) as usize] as char);
+        out.push(TABLE[(n >> 12 & 63) as usize] as char);
+        out.push(if chunk.len() > 1 {
+            TABLE[(n >> 6 & 63) as usize] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            TABLE[(n & 63) as usize] as char
+        } else {
+            '='
+        });
+    }
+    out
+}